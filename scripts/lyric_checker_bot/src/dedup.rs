@@ -0,0 +1,282 @@
+//! 提交前的语义去重检测。
+//!
+//! 在 TTML 解析通过之后，将提交的歌词文本编码为向量并与已有歌词库做最近邻检索，
+//! 避免为已经存在的歌词重复开启 PR。
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use candle_core::{Device, Tensor};
+use candle_transformers::models::bert::BertModel;
+use tokenizers::Tokenizer;
+use ttml_processor::types::LyricLine;
+
+/// 超过该阈值视为同一份歌词，直接跳过 PR 创建。
+pub const DUPLICATE_THRESHOLD: f32 = 0.95;
+/// 超过该阈值但未达 [`DUPLICATE_THRESHOLD`] 时，仍创建 PR 但附带“疑似重复”提示。
+pub const POSSIBLE_DUPLICATE_THRESHOLD: f32 = 0.85;
+
+/// 一次最近邻检索的结果。
+#[derive(Debug, Clone)]
+pub struct DuplicateMatch {
+    pub path: PathBuf,
+    pub similarity: f32,
+}
+
+/// 磁盘上的向量索引：文件路径 -> 归一化后的 embedding。
+///
+/// 索引以 JSON 形式持久化在仓库内（`.dedup_index.json`），随 `root_path` 下的 TTML
+/// 文件增量重建，避免每次运行都重新嵌入整个歌词库。
+pub struct DuplicateIndex {
+    index_path: PathBuf,
+    entries: HashMap<String, Vec<f32>>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Default)]
+struct StoredIndex {
+    entries: HashMap<String, Vec<f32>>,
+}
+
+impl DuplicateIndex {
+    /// 从 `root_path/.dedup_index.json` 加载已有索引，不存在时视为空索引。
+    pub fn load(root_path: &Path) -> Result<Self> {
+        let index_path = root_path.join(".dedup_index.json");
+        let entries = if index_path.exists() {
+            let raw = std::fs::read_to_string(&index_path)
+                .with_context(|| format!("读取去重索引失败: {:?}", index_path))?;
+            serde_json::from_str::<StoredIndex>(&raw)
+                .context("解析去重索引失败")?
+                .entries
+        } else {
+            HashMap::new()
+        };
+
+        Ok(Self {
+            index_path,
+            entries,
+        })
+    }
+
+    /// 将索引写回磁盘。
+    pub fn save(&self) -> Result<()> {
+        let stored = StoredIndex {
+            entries: self.entries.clone(),
+        };
+        let raw = serde_json::to_string(&stored).context("序列化去重索引失败")?;
+        std::fs::write(&self.index_path, raw).context("写入去重索引失败")
+    }
+
+    /// 增量更新：仅重新嵌入 `root_path` 下尚未出现在索引中的 TTML 文件。
+    pub fn rebuild_incremental(&mut self, root_path: &Path, embedder: &Embedder) -> Result<()> {
+        for entry in walk_ttml_files(root_path)? {
+            let key = entry
+                .strip_prefix(root_path)
+                .unwrap_or(&entry)
+                .to_string_lossy()
+                .to_string();
+            if self.entries.contains_key(&key) {
+                continue;
+            }
+            let content = std::fs::read_to_string(&entry)
+                .with_context(|| format!("读取歌词文件失败: {:?}", entry))?;
+            if let Ok(data) = ttml_processor::parse_ttml_content(
+                &content,
+                &ttml_processor::types::DefaultLanguageOptions::default(),
+            ) {
+                let text = submission_text(&data.lines);
+                let vector = embedder.embed(&text)?;
+                self.entries.insert(key, vector);
+            }
+        }
+        Ok(())
+    }
+
+    /// 按余弦相似度查找最近邻，结果按相似度降序排列。
+    pub fn nearest(&self, query: &[f32]) -> Vec<DuplicateMatch> {
+        let mut matches: Vec<DuplicateMatch> = self
+            .entries
+            .iter()
+            .map(|(path, vector)| DuplicateMatch {
+                path: PathBuf::from(path),
+                similarity: cosine_similarity(query, vector),
+            })
+            .collect();
+        matches.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+        matches
+    }
+}
+
+fn walk_ttml_files(root_path: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut stack = vec![root_path.to_path_buf()];
+    while let Some(dir) = stack.pop() {
+        for entry in std::fs::read_dir(&dir).with_context(|| format!("遍历目录失败: {:?}", dir))? {
+            let entry = entry?;
+            let path = entry.path();
+            if path.is_dir() {
+                if path.file_name().is_some_and(|n| n == ".git" || n == "target") {
+                    continue;
+                }
+                stack.push(path);
+            } else if path.extension().is_some_and(|ext| ext == "ttml") {
+                files.push(path);
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// 将提交的歌词行按时间顺序拼接为确定性的文本表示，用于生成 embedding。
+///
+/// 关键不变量：同一份歌词无论何时运行都必须映射到同一段文本，因此这里按
+/// `start_ms` 排序、统一大小写并折叠连续空白，不依赖原始文件的格式细节。
+pub fn submission_text(lines: &[LyricLine]) -> String {
+    let mut sorted_lines = lines.to_vec();
+    sorted_lines.sort_by_key(|line| line.start_ms);
+
+    let mut normalized = String::new();
+    for line in &sorted_lines {
+        let line_text: String = line.words.iter().map(|w| w.text.as_str()).collect();
+        let line_text = line_text.to_lowercase();
+        for token in line_text.split_whitespace() {
+            normalized.push_str(token);
+            normalized.push(' ');
+        }
+        normalized.push('\n');
+    }
+    normalized
+}
+
+/// 基于 `candle` 的轻量句向量模型封装。
+pub struct Embedder {
+    model: BertModel,
+    tokenizer: Tokenizer,
+    device: Device,
+    /// 模型的位置编码上限，长于此长度的输入会被截断，避免越界 panic。
+    max_seq_len: usize,
+}
+
+impl Embedder {
+    /// 加载本地的小型 sentence-embedding 模型权重及其配套的 tokenizer。
+    pub fn load(model_dir: &Path) -> Result<Self> {
+        let device = Device::Cpu;
+        let config_path = model_dir.join("config.json");
+        let weights_path = model_dir.join("model.safetensors");
+        let tokenizer_path = model_dir.join("tokenizer.json");
+        let config_raw = std::fs::read_to_string(&config_path)
+            .with_context(|| format!("读取模型配置失败: {:?}", config_path))?;
+        let config: candle_transformers::models::bert::Config =
+            serde_json::from_str(&config_raw).context("解析模型配置失败")?;
+        let tokenizer = Tokenizer::from_file(&tokenizer_path)
+            .map_err(|e| anyhow::anyhow!("加载 tokenizer 失败: {:?}: {e}", tokenizer_path))?;
+        let vb = unsafe {
+            candle_nn::VarBuilder::from_mmaped_safetensors(&[weights_path], candle_core::DType::F32, &device)
+                .context("加载模型权重失败")?
+        };
+        let model = BertModel::load(vb, &config).context("构建 BERT 模型失败")?;
+        Ok(Self {
+            model,
+            tokenizer,
+            device,
+            max_seq_len: config.max_position_embeddings,
+        })
+    }
+
+    /// 对归一化后的文本生成归一化 embedding 向量。
+    pub fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        let token_ids = self.tokenize(text);
+        let input = Tensor::new(&token_ids[..], &self.device)?.unsqueeze(0)?;
+        let token_type_ids = input.zeros_like()?;
+        let output = self.model.forward(&input, &token_type_ids, None)?;
+        let (_, seq_len, _) = output.dims3()?;
+        let pooled = (output.sum(1)? / seq_len as f64)?;
+        let pooled = pooled.squeeze(0)?;
+        let norm = pooled.sqr()?.sum_all()?.sqrt()?.to_scalar::<f32>()?;
+        let normalized = (pooled / norm as f64)?;
+        Ok(normalized.to_vec1()?)
+    }
+
+    /// 用模型自带的 WordPiece/BPE tokenizer 编码文本，并截断到 `max_seq_len`，
+    /// 避免超长歌词的 token 序列越过模型位置编码表的边界导致 panic。
+    fn tokenize(&self, text: &str) -> Vec<u32> {
+        let encoding = match self.tokenizer.encode(text, true) {
+            Ok(encoding) => encoding,
+            Err(e) => {
+                log::warn!("分词失败，回退为空序列: {e}");
+                return Vec::new();
+            }
+        };
+        let mut ids = encoding.get_ids().to_vec();
+        ids.truncate(self.max_seq_len);
+        ids
+    }
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() || a.is_empty() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cosine_similarity_of_identical_vectors_is_one() {
+        let v = vec![1.0, 2.0, 3.0];
+        assert!((cosine_similarity(&v, &v) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn cosine_similarity_of_orthogonal_vectors_is_zero() {
+        let a = vec![1.0, 0.0];
+        let b = vec![0.0, 1.0];
+        assert!(cosine_similarity(&a, &b).abs() < 1e-6);
+    }
+
+    #[test]
+    fn cosine_similarity_handles_zero_vectors_and_length_mismatch() {
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 0.0]), 0.0);
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[1.0, 0.0, 0.0]), 0.0);
+        assert_eq!(cosine_similarity(&[], &[]), 0.0);
+    }
+
+    #[test]
+    fn nearest_sorts_matches_by_similarity_descending() {
+        let mut entries = HashMap::new();
+        entries.insert("a.ttml".to_string(), vec![1.0, 0.0]);
+        entries.insert("b.ttml".to_string(), vec![0.0, 1.0]);
+        entries.insert("c.ttml".to_string(), vec![0.9, 0.1]);
+        let index = DuplicateIndex {
+            index_path: PathBuf::from("/tmp/does-not-matter.json"),
+            entries,
+        };
+
+        let matches = index.nearest(&[1.0, 0.0]);
+        assert_eq!(matches.len(), 3);
+        assert_eq!(matches[0].path, PathBuf::from("a.ttml"));
+        assert!(matches[0].similarity >= matches[1].similarity);
+        assert!(matches[1].similarity >= matches[2].similarity);
+    }
+}
+
+/// 对一次提交执行去重检测，返回相似度最高的既有文件（若有）。
+pub fn check_duplicate(
+    lines: &[LyricLine],
+    embedder: &Embedder,
+    index: &DuplicateIndex,
+) -> Result<Option<DuplicateMatch>> {
+    let text = submission_text(lines);
+    let vector = embedder.embed(&text)?;
+    Ok(index.nearest(&vector).into_iter().next())
+}
@@ -0,0 +1,250 @@
+//! 处理指向 Git 仓库的歌词提交来源。
+//!
+//! 除了直接的下载直链外，提交者还可以指定一个 Git 仓库地址、分支或提交（二选一），
+//! 以及仓库内的文件路径，机器人据此拉取对应版本下的 TTML 内容再走既有的
+//! 解析 -> 验证 -> 生成流程，方便提交者固定到某个具体提交以保证可复现。
+
+use std::path::{Component, Path};
+
+use anyhow::{Context, Result, bail};
+
+/// 从 Issue 表单中解析出的 Git 来源信息。
+#[derive(Debug, Clone)]
+pub struct GitSource {
+    pub repo_url: String,
+    pub branch: Option<String>,
+    pub revision: Option<String>,
+    pub file_path: String,
+}
+
+impl GitSource {
+    /// 校验字段组合是否合法：
+    /// - 地址与路径不能为空，分支与提交不能同时指定；
+    /// - `file_path` 必须是克隆目录内的相对路径，不能包含 `..` 或以 `/` 开头；
+    /// - `repo_url`/`branch`/`revision` 不能以 `-` 开头，避免被当作 `git` 的命令行选项注入。
+    pub fn validate(&self) -> Result<()> {
+        if self.repo_url.trim().is_empty() {
+            bail!("Git 仓库地址不能为空");
+        }
+        if self.file_path.trim().is_empty() {
+            bail!("仓库内的文件路径不能为空");
+        }
+        if self.branch.is_some() && self.revision.is_some() {
+            bail!("分支与提交（revision）只能二选一");
+        }
+
+        for (label, value) in [
+            ("Git 仓库地址", Some(self.repo_url.as_str())),
+            ("Git 分支", self.branch.as_deref()),
+            ("Git 提交", self.revision.as_deref()),
+        ] {
+            if value.is_some_and(|v| v.starts_with('-')) {
+                bail!("{} 不能以 '-' 开头", label);
+            }
+        }
+
+        let file_path = Path::new(&self.file_path);
+        if file_path.is_absolute()
+            || file_path
+                .components()
+                .any(|c| matches!(c, Component::ParentDir))
+        {
+            bail!("仓库内的文件路径必须是不包含 '..' 的相对路径");
+        }
+
+        Ok(())
+    }
+}
+
+/// 浅克隆或拉取指定版本，并读取仓库内给定路径的文件内容。
+///
+/// 未指定分支或提交时使用仓库的默认分支。
+pub async fn fetch_ttml_from_git(source: &GitSource) -> Result<String> {
+    source.validate()?;
+
+    let temp_dir = tempfile::tempdir().context("创建临时目录失败")?;
+    let repo_path = temp_dir.path();
+
+    clone_revision(source, repo_path).await?;
+
+    let target_path = repo_path.join(&source.file_path);
+    read_file_within(repo_path, &target_path)
+        .with_context(|| format!("在克隆的仓库中读取文件失败: {}", source.file_path))
+}
+
+/// 规范化 `target_path` 并确认其（在解析所有符号链接之后）仍位于 `repo_path`
+/// 之内，再读取文件内容。`validate()` 只保证了路径语法上不含 `..`，但提交者
+/// 克隆的仓库里完全可能提交一个指向仓库外的符号链接（例如指向 `/etc/passwd`
+/// 或 `/proc/self/environ`）——`fs::read_to_string` 会原样跟随符号链接，若不
+/// 在这里拦截，读出的宿主机文件内容最终会经由解析失败的拒绝评论被公开发表
+/// 到 Issue 里，造成任意文件泄露。
+fn read_file_within(repo_path: &Path, target_path: &Path) -> Result<String> {
+    let canonical_repo = std::fs::canonicalize(repo_path)
+        .with_context(|| format!("规范化仓库目录失败: {:?}", repo_path))?;
+    let canonical_target = std::fs::canonicalize(target_path)
+        .with_context(|| format!("规范化目标文件路径失败: {:?}", target_path))?;
+
+    if !canonical_target.starts_with(&canonical_repo) {
+        bail!("目标文件路径解析后逃逸出了克隆的仓库目录（可能是符号链接）");
+    }
+
+    std::fs::read_to_string(&canonical_target).context("读取文件内容失败")
+}
+
+/// 按 `branch`/`revision` 二选一的语义完成浅克隆，缺省时拉取默认分支。
+async fn clone_revision(source: &GitSource, dest: &Path) -> Result<()> {
+    if let Some(revision) = &source.revision {
+        run_git(&["init", "--quiet"], Some(dest)).await?;
+        run_git(
+            &["remote", "add", "--", "origin", &source.repo_url],
+            Some(dest),
+        )
+        .await?;
+        run_git(
+            &["fetch", "--depth", "1", "--", "origin", revision],
+            Some(dest),
+        )
+        .await?;
+        run_git(&["checkout", "--quiet", "FETCH_HEAD"], Some(dest)).await?;
+        return Ok(());
+    }
+
+    let mut args = vec![
+        "clone".to_string(),
+        "--depth".to_string(),
+        "1".to_string(),
+    ];
+    if let Some(branch) = &source.branch {
+        args.push("--branch".to_string());
+        args.push(branch.clone());
+    }
+    args.push("--".to_string());
+    args.push(source.repo_url.clone());
+    args.push(dest.to_string_lossy().to_string());
+
+    let arg_refs: Vec<&str> = args.iter().map(String::as_str).collect();
+    run_git(&arg_refs, None).await
+}
+
+async fn run_git(args: &[&str], cwd: Option<&Path>) -> Result<()> {
+    let mut command = tokio::process::Command::new("git");
+    command.args(args);
+    if let Some(cwd) = cwd {
+        command.current_dir(cwd);
+    }
+
+    let output = command
+        .output()
+        .await
+        .with_context(|| format!("执行 git {:?} 失败", args))?;
+
+    if !output.status.success() {
+        bail!(
+            "git {:?} 执行失败: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(repo_url: &str, branch: Option<&str>, revision: Option<&str>, file_path: &str) -> GitSource {
+        GitSource {
+            repo_url: repo_url.to_string(),
+            branch: branch.map(str::to_string),
+            revision: revision.map(str::to_string),
+            file_path: file_path.to_string(),
+        }
+    }
+
+    #[test]
+    fn accepts_well_formed_source() {
+        let s = source("https://example.com/repo.git", Some("main"), None, "lyrics/a.ttml");
+        assert!(s.validate().is_ok());
+    }
+
+    #[test]
+    fn rejects_empty_repo_url_or_file_path() {
+        assert!(source("", None, None, "a.ttml").validate().is_err());
+        assert!(source("https://example.com/repo.git", None, None, "").validate().is_err());
+    }
+
+    #[test]
+    fn rejects_branch_and_revision_together() {
+        let s = source("https://example.com/repo.git", Some("main"), Some("deadbeef"), "a.ttml");
+        assert!(s.validate().is_err());
+    }
+
+    #[test]
+    fn rejects_dash_prefixed_fields_to_prevent_argument_injection() {
+        assert!(
+            source("--upload-pack=evil", None, None, "a.ttml")
+                .validate()
+                .is_err()
+        );
+        assert!(
+            source("https://example.com/repo.git", Some("--exec=evil"), None, "a.ttml")
+                .validate()
+                .is_err()
+        );
+        assert!(
+            source("https://example.com/repo.git", None, Some("-x"), "a.ttml")
+                .validate()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn rejects_absolute_or_traversing_file_path() {
+        assert!(
+            source("https://example.com/repo.git", None, None, "/etc/passwd")
+                .validate()
+                .is_err()
+        );
+        assert!(
+            source("https://example.com/repo.git", None, None, "../../etc/passwd")
+                .validate()
+                .is_err()
+        );
+        assert!(
+            source("https://example.com/repo.git", None, None, "lyrics/../../escape.ttml")
+                .validate()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn rejects_symlink_escaping_the_repo_directory() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let repo_path = temp_dir.path();
+
+        let outside_dir = tempfile::tempdir().unwrap();
+        let outside_secret = outside_dir.path().join("secret.txt");
+        std::fs::write(&outside_secret, "不应被读取到").unwrap();
+
+        let link_path = repo_path.join("lyrics.ttml");
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&outside_secret, &link_path).unwrap();
+
+        #[cfg(unix)]
+        {
+            let err = read_file_within(repo_path, &link_path).unwrap_err();
+            assert!(err.to_string().contains("逃逸"));
+        }
+    }
+
+    #[test]
+    fn allows_regular_file_within_repo_directory() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let repo_path = temp_dir.path();
+        let file_path = repo_path.join("lyrics.ttml");
+        std::fs::write(&file_path, "内容").unwrap();
+
+        let content = read_file_within(repo_path, &file_path).unwrap();
+        assert_eq!(content, "内容");
+    }
+}
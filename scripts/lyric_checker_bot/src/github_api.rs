@@ -0,0 +1,269 @@
+//! 与 GitHub 交互的薄封装：拉取待处理 Issue、解析表单正文、发表评论/打标签，
+//! 以及在校验通过后创建提交 PR。
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use octocrab::Octocrab;
+use octocrab::models::issues::Issue;
+use ttml_processor::MetadataStore;
+
+const EXPERIMENTAL_LABEL: &str = "实验性歌词提交/修正";
+const BOT_COMMENT_MARKER: &str = "<!-- amll-ttml-db-bot -->";
+
+/// 对 `octocrab` 客户端的轻量封装，持有目标仓库的 owner/repo，便于在各处复用。
+#[derive(Clone)]
+pub struct GitHubClient {
+    client: Octocrab,
+    owner: String,
+    repo: String,
+}
+
+impl GitHubClient {
+    pub fn new(token: String, owner: String, repo: String) -> Result<Self> {
+        let client = Octocrab::builder()
+            .personal_token(token)
+            .build()
+            .context("构建 GitHub API 客户端失败")?;
+        Ok(Self {
+            client,
+            owner,
+            repo,
+        })
+    }
+
+    /// 列出所有带 `实验性歌词提交/修正` 标签、仍处于打开状态的 Issue。
+    pub async fn list_experimental_issues(&self) -> Result<Vec<Issue>> {
+        let page = self
+            .client
+            .issues(&self.owner, &self.repo)
+            .list()
+            .labels(&[EXPERIMENTAL_LABEL.to_string()])
+            .state(octocrab::params::State::Open)
+            .send()
+            .await
+            .context("获取 Issue 列表失败")?;
+        Ok(self
+            .client
+            .all_pages(page)
+            .await
+            .context("翻页获取 Issue 列表失败")?)
+    }
+
+    /// 指定 Issue 是否已经存在关联的 PR（通过 PR 正文中的 `Closes #<number>` 标记判定）。
+    pub async fn pr_for_issue_exists(&self, issue_number: u64) -> Result<bool> {
+        let marker = format!("Closes #{}", issue_number);
+        let page = self
+            .client
+            .pulls(&self.owner, &self.repo)
+            .list()
+            .state(octocrab::params::State::All)
+            .send()
+            .await
+            .context("获取 PR 列表失败")?;
+        let pulls = self
+            .client
+            .all_pages(page)
+            .await
+            .context("翻页获取 PR 列表失败")?;
+        Ok(pulls
+            .iter()
+            .any(|pr| pr.body.as_deref().is_some_and(|body| body.contains(&marker))))
+    }
+
+    /// 该 Issue 下是否已经有机器人发表过的评论（通过隐藏标记识别）。
+    pub async fn has_bot_commented(&self, issue_number: u64) -> Result<bool> {
+        let page = self
+            .client
+            .issues(&self.owner, &self.repo)
+            .list_comments(issue_number)
+            .send()
+            .await
+            .context("获取 Issue 评论失败")?;
+        let comments = self
+            .client
+            .all_pages(page)
+            .await
+            .context("翻页获取 Issue 评论失败")?;
+        Ok(comments
+            .iter()
+            .any(|comment| comment.body.as_deref().is_some_and(|b| b.contains(BOT_COMMENT_MARKER))))
+    }
+
+    /// 将 GitHub issue-form 渲染出的正文解析为 `字段标签 -> 取值` 的映射。
+    ///
+    /// issue-form 渲染格式形如：
+    /// ```text
+    /// ### 字段标签
+    ///
+    /// 字段取值
+    /// ```
+    pub fn parse_issue_body(&self, body: &str) -> std::collections::HashMap<String, String> {
+        let mut params = std::collections::HashMap::new();
+        let mut current_label: Option<String> = None;
+        let mut current_value = String::new();
+
+        for line in body.lines() {
+            if let Some(label) = line.strip_prefix("### ") {
+                if let Some(prev_label) = current_label.take() {
+                    params.insert(prev_label, current_value.trim().to_string());
+                }
+                current_label = Some(label.trim().to_string());
+                current_value.clear();
+            } else if current_label.is_some() {
+                current_value.push_str(line);
+                current_value.push('\n');
+            }
+        }
+        if let Some(label) = current_label {
+            params.insert(label, current_value.trim().to_string());
+        }
+
+        params
+    }
+
+    /// 发表一条拒绝评论，说明原因；若提供了原始 TTML 内容，附在折叠块中便于排查。
+    pub async fn post_decline_comment(
+        &self,
+        issue_number: u64,
+        reason: &str,
+        original_content: &str,
+    ) -> Result<()> {
+        let mut body = format!("{}\n提交未能通过自动检查：\n\n{}\n", BOT_COMMENT_MARKER, reason);
+        if !original_content.is_empty() {
+            body.push_str(&format!(
+                "\n<details><summary>原始提交内容</summary>\n\n```xml\n{}\n```\n\n</details>\n",
+                original_content
+            ));
+        }
+
+        self.client
+            .issues(&self.owner, &self.repo)
+            .create_comment(issue_number, body)
+            .await
+            .context("发表拒绝评论失败")?;
+        Ok(())
+    }
+
+    /// 给 Issue 附加一个标签（标签不存在时由 GitHub 端报错，调用方可按需忽略）。
+    pub async fn add_label(&self, issue_number: u64, label: &str) -> Result<()> {
+        self.client
+            .issues(&self.owner, &self.repo)
+            .add_labels(issue_number, &[label.to_string()])
+            .await
+            .context("添加标签失败")?;
+        Ok(())
+    }
+
+    /// 在校验通过后，创建提交分支、生成的 TTML 文件与对应的 PR。
+    pub async fn post_success_and_create_pr(&self, ctx: &PrContext<'_>) -> Result<()> {
+        let branch_name = format!("submission/issue-{}", ctx.issue.number);
+        let base_branch = self
+            .client
+            .repos(&self.owner, &self.repo)
+            .get()
+            .await
+            .context("获取仓库默认分支失败")?
+            .default_branch
+            .unwrap_or_else(|| "main".to_string());
+
+        let base_ref = self
+            .client
+            .repos(&self.owner, &self.repo)
+            .get_ref(&octocrab::params::repos::Reference::Branch(
+                base_branch.clone(),
+            ))
+            .await
+            .context("获取基准分支引用失败")?;
+        let base_sha = match base_ref.object {
+            octocrab::models::repos::Object::Commit { sha, .. } => sha,
+            octocrab::models::repos::Object::Tag { sha, .. } => sha,
+            _ => anyhow::bail!("无法解析基准分支的提交 SHA"),
+        };
+
+        self.client
+            .repos(&self.owner, &self.repo)
+            .create_ref(
+                &octocrab::params::repos::Reference::Branch(branch_name.clone()),
+                base_sha,
+            )
+            .await
+            .context("创建提交分支失败")?;
+
+        let file_name = format!("issue-{}.ttml", ctx.issue.number);
+        let file_path = ctx.root_path.join("ncm-lyrics").join(&file_name);
+        let repo_relative_path = file_path
+            .strip_prefix(ctx.root_path)
+            .unwrap_or(&file_path)
+            .to_string_lossy()
+            .to_string();
+
+        self.client
+            .repos(&self.owner, &self.repo)
+            .create_file(
+                &repo_relative_path,
+                format!("提交歌词: {}", file_name),
+                ctx.formatted_ttml,
+            )
+            .branch(&branch_name)
+            .send()
+            .await
+            .context("提交生成的 TTML 文件失败")?;
+
+        let mut pr_body = format!(
+            "Closes #{}\n\n{}\n",
+            ctx.issue.number,
+            if ctx.remarks.is_empty() {
+                "（无备注）".to_string()
+            } else {
+                ctx.remarks.to_string()
+            }
+        );
+        if let Some(warning) = ctx.duplicate_warning {
+            pr_body.push_str(&format!("\n> ⚠️ {}\n", warning));
+        }
+        if !ctx.warnings.is_empty() {
+            pr_body.push_str(&format!(
+                "\n解析警告：\n- {}\n",
+                ctx.warnings.join("\n- ")
+            ));
+        }
+
+        self.client
+            .pulls(&self.owner, &self.repo)
+            .create(
+                format!("歌词提交: Issue #{}", ctx.issue.number),
+                branch_name,
+                base_branch,
+            )
+            .body(pr_body)
+            .send()
+            .await
+            .context("创建 PR 失败")?;
+
+        self.client
+            .issues(&self.owner, &self.repo)
+            .create_comment(
+                ctx.issue.number,
+                format!("{}\n已创建 PR，感谢提交！", BOT_COMMENT_MARKER),
+            )
+            .await
+            .context("发表成功评论失败")?;
+
+        Ok(())
+    }
+}
+
+/// 创建 PR 所需的全部上下文，字段借用自调用方以避免不必要的拷贝。
+pub struct PrContext<'a> {
+    pub issue: &'a Issue,
+    pub original_ttml: &'a str,
+    pub compact_ttml: &'a str,
+    pub formatted_ttml: &'a str,
+    pub metadata_store: &'a MetadataStore,
+    pub remarks: &'a str,
+    pub warnings: &'a [String],
+    pub root_path: &'a Path,
+    /// 语义去重检测给出的“疑似重复”提示；未命中时为 `None`。
+    pub duplicate_warning: Option<&'a str>,
+}
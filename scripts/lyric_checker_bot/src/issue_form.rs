@@ -0,0 +1,514 @@
+//! 基于表单 Schema 的 Issue 正文解析。
+//!
+//! 替代原先分散在 `main.rs` 中的 `.contains()` 判断与悄悄吞掉解析失败的
+//! `get_param!` 宏：表单的字段、类型、可选项与默认值在 `.github/ISSUE_TEMPLATE/experimental_lyrics.yml`
+//! 中声明一次（数值字段的取值范围由本模块校验），本模块据此将渲染后的 Issue 正文解析为带类型的 `ParsedSubmission`，
+//! 并把每个字段各自的校验错误收集起来，而不是静默回退到默认值。
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use ttml_processor::types::TtmlTimingMode;
+
+/// GitHub issue-form 字段类型，与 `.github/ISSUE_TEMPLATE/*.yml` 中的 `type` 一一对应。
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldType {
+    Input,
+    Textarea,
+    Dropdown,
+    Checkboxes,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct FieldAttributes {
+    pub label: String,
+    #[serde(default)]
+    pub options: Vec<serde_yaml::Value>,
+    #[serde(default)]
+    pub default: Option<serde_yaml::Value>,
+}
+
+impl FieldAttributes {
+    /// 取出 `options` 中每一项可展示的文本：下拉框的选项是裸字符串，复选框的选项
+    /// 是带 `label` 字段的映射，两者都折叠成字符串列表供校验使用。
+    pub fn option_labels(&self) -> Vec<String> {
+        self.options
+            .iter()
+            .filter_map(|value| {
+                value
+                    .as_str()
+                    .map(str::to_string)
+                    .or_else(|| value.get("label")?.as_str().map(str::to_string))
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct FieldSchema {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub field_type: FieldType,
+    pub attributes: FieldAttributes,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+struct FormDefinition {
+    body: Vec<FieldSchema>,
+}
+
+/// 单个字段在解析过程中产生的校验错误。
+#[derive(Debug, Clone)]
+pub struct FieldError {
+    pub field_label: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for FieldError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "字段「{}」：{}", self.field_label, self.message)
+    }
+}
+
+/// 从仓库内的 issue-form 定义加载字段 Schema。
+pub fn load_schema(root_path: &Path) -> Result<Vec<FieldSchema>> {
+    let schema_path = root_path.join(".github/ISSUE_TEMPLATE/experimental_lyrics.yml");
+    let raw = std::fs::read_to_string(&schema_path)
+        .with_context(|| format!("读取 issue-form 定义失败: {:?}", schema_path))?;
+    let definition: FormDefinition =
+        serde_yaml::from_str(&raw).context("解析 issue-form 定义失败")?;
+    Ok(definition.body)
+}
+
+/// 解析出的、带类型的提交表单。
+#[derive(Debug, Clone)]
+pub struct ParsedSubmission {
+    pub ttml_url: Option<String>,
+    pub git_repo_url: Option<String>,
+    pub git_branch: Option<String>,
+    pub git_revision: Option<String>,
+    pub git_file_path: Option<String>,
+    pub remarks: String,
+    pub timing_mode: TtmlTimingMode,
+    pub enable_smoothing: bool,
+    pub auto_split: bool,
+    pub smoothing_factor: f64,
+    pub smoothing_duration_threshold_ms: u64,
+    pub smoothing_gap_threshold_ms: u64,
+    pub smoothing_iterations: u32,
+    pub punctuation_weight: f64,
+}
+
+/// 依据 `schema` 将渲染后的 Issue 正文字段解析为 [`ParsedSubmission`]。
+///
+/// 每个字段独立校验：取值范围越界、下拉框选项未知、数字无法解析都会各自产生一条
+/// [`FieldError`]，而不是像旧的 `get_param!` 宏那样静默回退到默认值。下拉框的合法
+/// 取值与数字字段的默认值都从 `schema`（即 issue-form 的 YAML 定义）读取，而不是
+/// 在这里重复硬编码，这样表单改了字段，解析逻辑会跟着变而不是悄悄脱节。
+pub fn parse_submission(
+    body_params: &HashMap<String, String>,
+    schema: &[FieldSchema],
+) -> (ParsedSubmission, Vec<FieldError>) {
+    let mut errors = Vec::new();
+    let field = |id: &str| schema.iter().find(|field| field.id == id);
+    let label_of = |id: &str| -> String {
+        field(id)
+            .map(|field| field.attributes.label.clone())
+            .unwrap_or_else(|| id.to_string())
+    };
+
+    let ttml_url = get_raw(body_params, &label_of("ttml_url"));
+    let git_repo_url = get_raw(body_params, &label_of("git_repo_url"));
+    let git_branch = get_raw(body_params, &label_of("git_branch"));
+    let git_revision = get_raw(body_params, &label_of("git_revision"));
+    let git_file_path = get_raw(body_params, &label_of("git_file_path"));
+
+    let remarks = get_raw(body_params, &label_of("remarks")).unwrap_or_default();
+
+    let lyric_options_value = dropdown_value(body_params, field("lyric_options"), &mut errors);
+    let timing_mode = match lyric_options_value.as_deref() {
+        Some(value) if value.contains("逐行") => TtmlTimingMode::Line,
+        _ => TtmlTimingMode::Word,
+    };
+
+    let selected_toggles = checkbox_values(body_params, field("feature_toggles"), &mut errors);
+    let enable_smoothing = selected_toggles.iter().any(|v| v == "启用平滑优化");
+    let auto_split = selected_toggles.iter().any(|v| v == "启用自动分词");
+
+    let smoothing_factor = parse_bounded_number(
+        body_params,
+        &label_of("smoothing_factor"),
+        schema_default_f64(field("smoothing_factor"), 0.15),
+        0.0..=1.0,
+        &mut errors,
+    );
+    let smoothing_duration_threshold_ms: u64 = parse_number(
+        body_params,
+        &label_of("smoothing_duration_threshold_ms"),
+        schema_default_u64(field("smoothing_duration_threshold_ms"), 50),
+        &mut errors,
+    );
+    let smoothing_gap_threshold_ms: u64 = parse_number(
+        body_params,
+        &label_of("smoothing_gap_threshold_ms"),
+        schema_default_u64(field("smoothing_gap_threshold_ms"), 100),
+        &mut errors,
+    );
+    let smoothing_iterations: u32 = parse_number(
+        body_params,
+        &label_of("smoothing_iterations"),
+        schema_default_u64(field("smoothing_iterations"), 5) as u32,
+        &mut errors,
+    );
+    let punctuation_weight = parse_bounded_number(
+        body_params,
+        &label_of("punctuation_weight"),
+        schema_default_f64(field("punctuation_weight"), 0.3),
+        0.0..=1.0,
+        &mut errors,
+    );
+
+    let parsed = ParsedSubmission {
+        ttml_url,
+        git_repo_url,
+        git_branch,
+        git_revision,
+        git_file_path,
+        remarks,
+        timing_mode,
+        enable_smoothing,
+        auto_split,
+        smoothing_factor,
+        smoothing_duration_threshold_ms,
+        smoothing_gap_threshold_ms,
+        smoothing_iterations,
+        punctuation_weight,
+    };
+    (parsed, errors)
+}
+
+/// 从 schema 中读取字段的数值默认值，字段未声明 `default` 时使用 `fallback`。
+fn schema_default_f64(field: Option<&FieldSchema>, fallback: f64) -> f64 {
+    field
+        .and_then(|field| field.attributes.default.as_ref())
+        .and_then(|value| value.as_f64())
+        .unwrap_or(fallback)
+}
+
+fn schema_default_u64(field: Option<&FieldSchema>, fallback: u64) -> u64 {
+    field
+        .and_then(|field| field.attributes.default.as_ref())
+        .and_then(|value| value.as_u64())
+        .unwrap_or(fallback)
+}
+
+/// 读取下拉框字段的原始取值，并按 `FieldType::Dropdown` + schema 声明的 `options`
+/// 校验其合法性；非下拉框字段不做选项校验，未在 schema 中声明的字段返回 `None`。
+fn dropdown_value(
+    body_params: &HashMap<String, String>,
+    field: Option<&FieldSchema>,
+    errors: &mut Vec<FieldError>,
+) -> Option<String> {
+    let field = field?;
+    let raw = get_raw(body_params, &field.attributes.label)?;
+
+    if field.field_type != FieldType::Dropdown {
+        return Some(raw);
+    }
+
+    let options = field.attributes.option_labels();
+    if options.iter().any(|opt| opt == &raw) {
+        Some(raw)
+    } else {
+        errors.push(FieldError {
+            field_label: field.attributes.label.clone(),
+            message: format!("未知的下拉选项取值: {}", raw),
+        });
+        None
+    }
+}
+
+/// 读取复选框字段渲染出的 Markdown 列表（每行形如 `- [x] 选项文本`），返回被勾选的
+/// 选项文本，并对照 `field.attributes.option_labels()` 校验每一项都是 schema 中
+/// 声明过的选项——未知的勾选项各自产生一条 [`FieldError`]，而不是像之前那样用
+/// `.contains()` 悄悄漏判拼错或过时的选项。
+fn checkbox_values(
+    body_params: &HashMap<String, String>,
+    field: Option<&FieldSchema>,
+    errors: &mut Vec<FieldError>,
+) -> Vec<String> {
+    let Some(field) = field else {
+        return Vec::new();
+    };
+    let Some(raw) = get_raw(body_params, &field.attributes.label) else {
+        return Vec::new();
+    };
+
+    if field.field_type != FieldType::Checkboxes {
+        return vec![raw];
+    }
+
+    let known_options = field.attributes.option_labels();
+    let mut checked = Vec::new();
+    for line in raw.lines() {
+        let line = line.trim();
+        let Some(rest) = line
+            .strip_prefix("- [x]")
+            .or_else(|| line.strip_prefix("- [X]"))
+        else {
+            continue;
+        };
+        let label = rest.trim().to_string();
+        if known_options.iter().any(|opt| opt == &label) {
+            checked.push(label);
+        } else {
+            errors.push(FieldError {
+                field_label: field.attributes.label.clone(),
+                message: format!("未知的复选框选项: {}", label),
+            });
+        }
+    }
+    checked
+}
+
+fn get_raw(body_params: &HashMap<String, String>, label: &str) -> Option<String> {
+    body_params
+        .get(label)
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty() && *s != "_No response_")
+        .map(str::to_string)
+}
+
+fn parse_number<T>(
+    body_params: &HashMap<String, String>,
+    label: &str,
+    default: T,
+    errors: &mut Vec<FieldError>,
+) -> T
+where
+    T: std::str::FromStr,
+{
+    match get_raw(body_params, label) {
+        None => default,
+        Some(raw) => raw.parse().unwrap_or_else(|_| {
+            errors.push(FieldError {
+                field_label: label.to_string(),
+                message: format!("无法解析为数字: {}", raw),
+            });
+            default
+        }),
+    }
+}
+
+fn parse_bounded_number(
+    body_params: &HashMap<String, String>,
+    label: &str,
+    default: f64,
+    range: std::ops::RangeInclusive<f64>,
+    errors: &mut Vec<FieldError>,
+) -> f64 {
+    match get_raw(body_params, label) {
+        None => default,
+        Some(raw) => match raw.parse::<f64>() {
+            Ok(value) if range.contains(&value) => value,
+            Ok(value) => {
+                errors.push(FieldError {
+                    field_label: label.to_string(),
+                    message: format!(
+                        "取值 {} 超出允许范围 [{}, {}]",
+                        value,
+                        range.start(),
+                        range.end()
+                    ),
+                });
+                default
+            }
+            Err(_) => {
+                errors.push(FieldError {
+                    field_label: label.to_string(),
+                    message: format!("无法解析为数字: {}", raw),
+                });
+                default
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dropdown_field(id: &str, label: &str, options: &[&str]) -> FieldSchema {
+        FieldSchema {
+            id: id.to_string(),
+            field_type: FieldType::Dropdown,
+            attributes: FieldAttributes {
+                label: label.to_string(),
+                options: options
+                    .iter()
+                    .map(|o| serde_yaml::Value::String(o.to_string()))
+                    .collect(),
+                default: None,
+            },
+        }
+    }
+
+    fn input_field_with_default(id: &str, label: &str, default: f64) -> FieldSchema {
+        FieldSchema {
+            id: id.to_string(),
+            field_type: FieldType::Input,
+            attributes: FieldAttributes {
+                label: label.to_string(),
+                options: Vec::new(),
+                default: Some(serde_yaml::Value::Number(default.into())),
+            },
+        }
+    }
+
+    #[test]
+    fn option_labels_reads_both_dropdown_and_checkbox_shapes() {
+        let dropdown = dropdown_field("opt", "选项", &["这是逐行歌词", "这是逐字歌词"]);
+        assert_eq!(
+            dropdown.attributes.option_labels(),
+            vec!["这是逐行歌词".to_string(), "这是逐字歌词".to_string()]
+        );
+
+        let checkboxes = FieldAttributes {
+            label: "功能开关".to_string(),
+            options: vec![serde_yaml::to_value(
+                [("label", "启用平滑优化")].into_iter().collect::<HashMap<_, _>>(),
+            )
+            .unwrap()],
+            default: None,
+        };
+        assert_eq!(checkboxes.option_labels(), vec!["启用平滑优化".to_string()]);
+    }
+
+    #[test]
+    fn dropdown_value_accepts_known_option_and_rejects_unknown() {
+        let field = dropdown_field("lyric_options", "歌词选项", &["这是逐行歌词", "这是逐字歌词"]);
+
+        let mut body = HashMap::new();
+        body.insert("歌词选项".to_string(), "这是逐行歌词".to_string());
+        let mut errors = Vec::new();
+        assert_eq!(
+            dropdown_value(&body, Some(&field), &mut errors),
+            Some("这是逐行歌词".to_string())
+        );
+        assert!(errors.is_empty());
+
+        let mut body = HashMap::new();
+        body.insert("歌词选项".to_string(), "胡乱填写的选项".to_string());
+        let mut errors = Vec::new();
+        assert_eq!(dropdown_value(&body, Some(&field), &mut errors), None);
+        assert_eq!(errors.len(), 1);
+    }
+
+    fn checkbox_field(id: &str, label: &str, options: &[&str]) -> FieldSchema {
+        FieldSchema {
+            id: id.to_string(),
+            field_type: FieldType::Checkboxes,
+            attributes: FieldAttributes {
+                label: label.to_string(),
+                options: options
+                    .iter()
+                    .map(|o| {
+                        serde_yaml::to_value([("label", *o)].into_iter().collect::<HashMap<_, _>>())
+                            .unwrap()
+                    })
+                    .collect(),
+                default: None,
+            },
+        }
+    }
+
+    #[test]
+    fn checkbox_values_collects_only_checked_known_options() {
+        let field = checkbox_field("feature_toggles", "功能开关", &["启用平滑优化", "启用自动分词"]);
+
+        let mut body = HashMap::new();
+        body.insert(
+            "功能开关".to_string(),
+            "- [x] 启用平滑优化\n- [ ] 启用自动分词".to_string(),
+        );
+        let mut errors = Vec::new();
+        let selected = checkbox_values(&body, Some(&field), &mut errors);
+        assert_eq!(selected, vec!["启用平滑优化".to_string()]);
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn checkbox_values_reports_unknown_checked_option() {
+        let field = checkbox_field("feature_toggles", "功能开关", &["启用平滑优化", "启用自动分词"]);
+
+        let mut body = HashMap::new();
+        body.insert(
+            "功能开关".to_string(),
+            "- [X] 启用平滑优化\n- [x] 一个不存在的开关".to_string(),
+        );
+        let mut errors = Vec::new();
+        let selected = checkbox_values(&body, Some(&field), &mut errors);
+        assert_eq!(selected, vec!["启用平滑优化".to_string()]);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].message.contains("一个不存在的开关"));
+    }
+
+    #[test]
+    fn schema_default_falls_back_when_field_has_no_default() {
+        let declared = input_field_with_default("smoothing_factor", "[平滑] 平滑因子", 0.2);
+        assert_eq!(schema_default_f64(Some(&declared), 0.15), 0.2);
+        assert_eq!(schema_default_f64(None, 0.15), 0.15);
+    }
+
+    #[test]
+    fn parse_bounded_number_rejects_out_of_range_value() {
+        let mut body = HashMap::new();
+        body.insert("权重".to_string(), "1.5".to_string());
+        let mut errors = Vec::new();
+        let value = parse_bounded_number(&body, "权重", 0.3, 0.0..=1.0, &mut errors);
+        assert_eq!(value, 0.3);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].message.contains("超出允许范围"));
+    }
+
+    #[test]
+    fn parse_submission_routes_git_fields_through_parsed_submission() {
+        let schema = vec![
+            FieldSchema {
+                id: "git_repo_url".to_string(),
+                field_type: FieldType::Input,
+                attributes: FieldAttributes {
+                    label: "Git 仓库地址".to_string(),
+                    options: Vec::new(),
+                    default: None,
+                },
+            },
+            FieldSchema {
+                id: "git_file_path".to_string(),
+                field_type: FieldType::Input,
+                attributes: FieldAttributes {
+                    label: "Git 内文件路径".to_string(),
+                    options: Vec::new(),
+                    default: None,
+                },
+            },
+        ];
+
+        let mut body = HashMap::new();
+        body.insert(
+            "Git 仓库地址".to_string(),
+            "https://example.com/lyrics.git".to_string(),
+        );
+        body.insert("Git 内文件路径".to_string(), "lyrics/a.ttml".to_string());
+
+        let (parsed, errors) = parse_submission(&body, &schema);
+        assert!(errors.is_empty());
+        assert_eq!(
+            parsed.git_repo_url,
+            Some("https://example.com/lyrics.git".to_string())
+        );
+        assert_eq!(parsed.git_file_path, Some("lyrics/a.ttml".to_string()));
+    }
+}
@@ -1,5 +1,10 @@
+mod dedup;
 mod git_utils;
 mod github_api;
+mod issue_form;
+mod mirrors;
+mod rules;
+mod webhook;
 
 use anyhow::Result;
 use chrono::Utc;
@@ -8,15 +13,24 @@ use log::LevelFilter;
 use reqwest::Client;
 use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Mutex;
 use ttml_processor::{
     MetadataStore, apply_smoothing, generate_ttml, parse_ttml_content,
-    types::{
-        DefaultLanguageOptions, SyllableSmoothingOptions, TtmlGenerationOptions, TtmlTimingMode,
-    },
+    types::{DefaultLanguageOptions, SyllableSmoothingOptions, TtmlGenerationOptions},
     validate_lyrics_and_metadata,
 };
 
+use crate::dedup::{DuplicateIndex, Embedder};
 use crate::github_api::PrContext;
+use crate::rules::RoutingRule;
+
+/// 语义去重所需的共享状态：模型常驻内存，索引在启动时增量重建一次，之后只在加锁下查询。
+#[derive(Clone)]
+pub struct DedupContext {
+    embedder: Arc<Embedder>,
+    index: Arc<Mutex<DuplicateIndex>>,
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -53,6 +67,41 @@ async fn main() -> Result<()> {
     let http_client = Client::new();
     let github = github_api::GitHubClient::new(token, owner.to_string(), repo_name.to_string())?;
 
+    let dedup_model_dir = std::env::var("DEDUP_MODEL_DIR").expect("未设置 DEDUP_MODEL_DIR");
+    let embedder = Embedder::load(Path::new(&dedup_model_dir))?;
+    let mut index = DuplicateIndex::load(&root_path)?;
+    index.rebuild_incremental(&root_path, &embedder)?;
+    index.save()?;
+    let dedup_ctx = DedupContext {
+        embedder: Arc::new(embedder),
+        index: Arc::new(Mutex::new(index)),
+    };
+
+    let rules_config_path = root_path.join(".github/routing_rules.txt");
+    let routing_rules: Arc<Vec<RoutingRule>> = Arc::new(if rules_config_path.exists() {
+        let raw = std::fs::read_to_string(&rules_config_path)?;
+        rules::load_rule_config(&raw)?
+    } else {
+        log::info!("未找到路由规则配置 {:?}，跳过规则求值。", rules_config_path);
+        Vec::new()
+    });
+
+    let serve_mode = std::env::args().any(|arg| arg == "--serve")
+        || std::env::var("SERVE_MODE").is_ok_and(|v| v == "1" || v.eq_ignore_ascii_case("true"));
+
+    if serve_mode {
+        let serve_config = webhook::ServeConfig::from_env()?;
+        return webhook::run_server(
+            serve_config,
+            http_client,
+            github,
+            root_path,
+            dedup_ctx,
+            routing_rules,
+        )
+        .await;
+    }
+
     log::info!("正在获取带 '实验性歌词提交/修正' 标签的 Issue...");
     let issues = github.list_experimental_issues().await?;
 
@@ -60,9 +109,13 @@ async fn main() -> Result<()> {
         let http_client = http_client.clone();
         let github = github.clone();
         let root_path = root_path.clone();
+        let dedup_ctx = dedup_ctx.clone();
+        let routing_rules = Arc::clone(&routing_rules);
 
         log::info!("开始处理 Issue #{}: {}", issue.number, issue.title);
-        if let Err(e) = process_issue(&issue, http_client, github, &root_path).await {
+        if let Err(e) =
+            process_issue(&issue, http_client, github, &root_path, dedup_ctx, routing_rules).await
+        {
             log::error!("处理 Issue #{} 失败: {:?}", issue.number, e);
         }
     }
@@ -77,6 +130,8 @@ async fn process_issue(
     http_client: Client,
     github: github_api::GitHubClient,
     root_path: &Path,
+    dedup_ctx: DedupContext,
+    routing_rules: Arc<Vec<RoutingRule>>,
 ) -> Result<()> {
     if github.pr_for_issue_exists(issue.number).await? {
         // 如果 PR 已存在，直接返回，不再处理
@@ -92,81 +147,87 @@ async fn process_issue(
     // 2. 解析 Issue Body
     let issue_body = issue.body.as_deref().unwrap_or("");
     let body_params = github.parse_issue_body(issue_body);
-    let ttml_url = match body_params.get("TTML 歌词文件下载直链") {
-        Some(url) if !url.is_empty() => url,
-        _ => {
+
+    let form_schema = issue_form::load_schema(root_path)?;
+    let (submission, field_errors) = issue_form::parse_submission(&body_params, &form_schema);
+    if !field_errors.is_empty() {
+        let err_msg = format!(
+            "表单字段校验失败:\n- {}",
+            field_errors
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join("\n- ")
+        );
+        github
+            .post_decline_comment(issue.number, &err_msg, "")
+            .await?;
+        return Ok(());
+    }
+
+    let ttml_source = match resolve_ttml_source(&submission) {
+        Ok(source) => source,
+        Err(e) => {
             github
-                .post_decline_comment(
-                    issue.number,
-                    "无法在 Issue 中找到有效的“TTML 歌词文件下载直链”。",
-                    "",
-                )
+                .post_decline_comment(issue.number, &e.to_string(), "")
                 .await?;
             return Ok(());
         }
     };
-    let remarks = body_params.get("备注").cloned().unwrap_or_default();
 
-    // 解析歌词选项
-    let lyric_options = body_params.get("歌词选项").cloned().unwrap_or_default();
-    let timing_mode = if lyric_options.contains("这是逐行歌词") {
-        TtmlTimingMode::Line
-    } else {
-        TtmlTimingMode::Word
-    };
+    let remarks = submission.remarks.clone();
+    let timing_mode = submission.timing_mode;
     log::info!("Issue #{} 使用计时模式: {:?}", issue.number, timing_mode);
 
-    let advanced_toggles = body_params.get("功能开关").cloned().unwrap_or_default();
-    let enable_smoothing = advanced_toggles.contains("启用平滑优化");
-    let auto_split = advanced_toggles.contains("启用自动分词");
+    let enable_smoothing = submission.enable_smoothing;
+    let auto_split = submission.auto_split;
 
     let smoothing_options = if enable_smoothing {
         log::info!("Issue #{} 已启用平滑优化。", issue.number);
-        macro_rules! get_param {
-            ($key:expr, $default:expr) => {
-                body_params
-                    .get($key)
-                    .and_then(|s| {
-                        if s.is_empty() || s == "_No response_" {
-                            None
-                        } else {
-                            s.parse().ok()
-                        }
-                    })
-                    .unwrap_or($default)
-            };
-        }
-
         Some(SyllableSmoothingOptions {
-            factor: get_param!("[平滑] 平滑因子", 0.15),
-            duration_threshold_ms: get_param!("[平滑] 分组时长差异阈值 (毫秒)", 50),
-            gap_threshold_ms: get_param!("[平滑] 分组间隔阈值 (毫秒)", 100),
-            smoothing_iterations: get_param!("[平滑] 迭代次数", 5),
+            factor: submission.smoothing_factor,
+            duration_threshold_ms: submission.smoothing_duration_threshold_ms,
+            gap_threshold_ms: submission.smoothing_gap_threshold_ms,
+            smoothing_iterations: submission.smoothing_iterations,
         })
     } else {
         None
     };
 
-    let punctuation_weight = if auto_split {
+    if auto_split {
         log::info!("Issue #{} 已启用自动分词。", issue.number);
-        body_params
-            .get("[分词] 标点符号权重")
-            .and_then(|s| {
-                if s.is_empty() || s == "_No response_" {
-                    None
-                } else {
-                    s.parse().ok()
+    }
+    let punctuation_weight = submission.punctuation_weight;
+
+    // 3. 获取 TTML 文件内容
+    let original_ttml_content = match &ttml_source {
+        TtmlSource::DirectUrl(url) => {
+            let mirror_config = mirrors::MirrorConfig::load(root_path)?;
+            mirrors::fetch_with_fallback(&http_client, url, &mirror_config).await?
+        }
+        TtmlSource::Git(source) => {
+            log::info!(
+                "正在从 Git 仓库获取 TTML: {} ({})",
+                source.repo_url,
+                source
+                    .revision
+                    .as_deref()
+                    .or(source.branch.as_deref())
+                    .unwrap_or("默认分支")
+            );
+            match git_utils::fetch_ttml_from_git(source).await {
+                Ok(content) => content,
+                Err(e) => {
+                    let err_msg = format!("从 Git 仓库获取 TTML 文件失败: {:?}", e);
+                    github
+                        .post_decline_comment(issue.number, &err_msg, "")
+                        .await?;
+                    return Ok(());
                 }
-            })
-            .unwrap_or(0.3)
-    } else {
-        0.3
+            }
+        }
     };
 
-    // 3. 下载 TTML 文件
-    log::info!("正在从 URL 下载 TTML: {}", ttml_url);
-    let original_ttml_content = http_client.get(ttml_url).send().await?.text().await?;
-
     log::info!("开始解析 TTML 文件...");
     let default_langs = DefaultLanguageOptions::default();
     let mut parsed_data = match parse_ttml_content(&original_ttml_content, &default_langs) {
@@ -220,6 +281,63 @@ async fn process_issue(
     }
     log::info!("文件验证通过。");
 
+    log::info!("正在按路由规则求值提交...");
+    let rule_ctx = rules::EvalContext {
+        metadata: &metadata_store,
+        lines: &parsed_data.lines,
+    };
+    match rules::first_matching_action(&routing_rules, &rule_ctx) {
+        Ok(Some(rules::RuleAction::Decline { message })) => {
+            github
+                .post_decline_comment(issue.number, &message, &original_ttml_content)
+                .await?;
+            return Ok(());
+        }
+        Ok(Some(rules::RuleAction::Label(label))) => {
+            log::info!("Issue #{} 命中规则，将附加标签: {}", issue.number, label);
+            github.add_label(issue.number, &label).await?;
+        }
+        Ok(Some(rules::RuleAction::RequireReview)) => {
+            log::info!("Issue #{} 命中规则，标记为需要人工复核。", issue.number);
+            github.add_label(issue.number, "需要人工复核").await?;
+        }
+        Ok(None) => {}
+        Err(e) => {
+            log::warn!("Issue #{} 路由规则求值失败，按未命中处理: {}", issue.number, e);
+        }
+    }
+
+    log::info!("正在检查是否与现有歌词重复...");
+    let duplicate_match = {
+        let index = dedup_ctx.index.lock().await;
+        dedup::check_duplicate(&parsed_data.lines, &dedup_ctx.embedder, &index)?
+    };
+    let mut duplicate_warning = None;
+    if let Some(found) = &duplicate_match {
+        log::info!(
+            "Issue #{} 最相似的既有文件: {:?} (相似度 {:.4})",
+            issue.number,
+            found.path,
+            found.similarity
+        );
+        if found.similarity >= dedup::DUPLICATE_THRESHOLD {
+            let msg = format!(
+                "该提交与仓库中已有的歌词文件 `{}` 高度相似，可能是重复提交。",
+                found.path.display()
+            );
+            github
+                .post_decline_comment(issue.number, &msg, &original_ttml_content)
+                .await?;
+            return Ok(());
+        } else if found.similarity >= dedup::POSSIBLE_DUPLICATE_THRESHOLD {
+            duplicate_warning = Some(format!(
+                "疑似重复：与 `{}` 的相似度为 {:.2}，请人工确认。",
+                found.path.display(),
+                found.similarity
+            ));
+        }
+    }
+
     log::info!("正在生成 TTML 文件...");
 
     log::info!("正在生成压缩的 TTML...");
@@ -254,8 +372,34 @@ async fn process_issue(
         remarks: &remarks,
         warnings: &parsed_data.warnings,
         root_path,
+        duplicate_warning: duplicate_warning.as_deref(),
     };
 
     github.post_success_and_create_pr(&pr_context).await?;
     Ok(())
 }
+
+/// 提交指向的 TTML 来源：既有的直链下载，或新支持的 Git 仓库。
+enum TtmlSource {
+    DirectUrl(String),
+    Git(git_utils::GitSource),
+}
+
+/// 根据解析后的表单字段判定提交使用的是直链还是 Git 仓库来源，并校验 Git 字段组合。
+fn resolve_ttml_source(submission: &issue_form::ParsedSubmission) -> Result<TtmlSource> {
+    if let Some(repo_url) = &submission.git_repo_url {
+        let source = git_utils::GitSource {
+            repo_url: repo_url.clone(),
+            branch: submission.git_branch.clone(),
+            revision: submission.git_revision.clone(),
+            file_path: submission.git_file_path.clone().unwrap_or_default(),
+        };
+        source.validate()?;
+        return Ok(TtmlSource::Git(source));
+    }
+
+    match &submission.ttml_url {
+        Some(url) => Ok(TtmlSource::DirectUrl(url.clone())),
+        None => anyhow::bail!("无法在 Issue 中找到有效的“TTML 歌词文件下载直链”或 Git 仓库地址。"),
+    }
+}
@@ -0,0 +1,176 @@
+//! 可配置的下载镜像/代理，带失败重试与超时。
+//!
+//! 提交者的直链常常指向常见的网盘或被 Action Runner 所在地区屏蔽的主机，单纯
+//! `http_client.get(url)` 会直接失败。本模块从仓库内的配置文件加载一组按 URL
+//! 模式匹配的镜像改写规则，依序尝试每个候选地址，直到下载成功或候选耗尽。
+
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use reqwest::Client;
+
+/// 单条镜像改写规则：当原始 URL 匹配 `pattern` 时，依次尝试 `rewrites` 中的每个候选。
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct MirrorRule {
+    /// 原始 URL 中需要出现的子串（而非完整正则），用于判断该规则是否适用。
+    pub pattern: String,
+    /// 按尝试顺序排列的候选地址；`{url}` 会被替换为原始 URL（经过 URL 编码）。
+    pub rewrites: Vec<String>,
+}
+
+/// 下载源配置：一组按顺序匹配的镜像规则，加上重试与超时参数。
+///
+/// 规则列表中永远包含一条隐式的“直接使用原始地址”候选，因此没有规则匹配时
+/// 行为与改动前完全一致。
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct MirrorConfig {
+    #[serde(default)]
+    pub rules: Vec<MirrorRule>,
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: usize,
+}
+
+fn default_timeout_secs() -> u64 {
+    15
+}
+
+fn default_max_attempts() -> usize {
+    3
+}
+
+impl Default for MirrorConfig {
+    fn default() -> Self {
+        Self {
+            rules: Vec::new(),
+            timeout_secs: default_timeout_secs(),
+            max_attempts: default_max_attempts(),
+        }
+    }
+}
+
+impl MirrorConfig {
+    /// 从仓库内 `.github/download_mirrors.toml` 加载配置；文件不存在时使用默认值
+    /// （无镜像规则，只尝试原始地址）。
+    pub fn load(root_path: &Path) -> Result<Self> {
+        let config_path = root_path.join(".github/download_mirrors.toml");
+        if !config_path.exists() {
+            return Ok(Self::default());
+        }
+        let raw = std::fs::read_to_string(&config_path)
+            .with_context(|| format!("读取镜像配置失败: {:?}", config_path))?;
+        toml::from_str(&raw).context("解析镜像配置失败")
+    }
+
+    /// 构造候选地址列表：命中的镜像规则的改写结果在前，原始地址始终作为最后的兜底。
+    ///
+    /// `max_attempts` 只截断镜像改写的数量，为原始地址保留一个名额——否则当改写
+    /// 候选数达到或超过 `max_attempts` 时，兜底的原始地址会被一并截断掉，与上面
+    /// “原始地址始终作为最后的兜底”的承诺相矛盾。
+    fn candidates(&self, original_url: &str) -> Vec<String> {
+        let mut rewrites = Vec::new();
+        for rule in &self.rules {
+            if original_url.contains(&rule.pattern) {
+                for rewrite in &rule.rewrites {
+                    rewrites.push(rewrite.replace("{url}", &urlencoding::encode(original_url)));
+                }
+            }
+        }
+        rewrites.truncate(self.max_attempts.max(1) - 1);
+        rewrites.push(original_url.to_string());
+        rewrites
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn original_url_is_always_present_even_when_rewrites_exceed_max_attempts() {
+        let config = MirrorConfig {
+            rules: vec![MirrorRule {
+                pattern: "example.com".to_string(),
+                rewrites: vec![
+                    "https://mirror-a/{url}".to_string(),
+                    "https://mirror-b/{url}".to_string(),
+                    "https://mirror-c/{url}".to_string(),
+                ],
+            }],
+            timeout_secs: default_timeout_secs(),
+            max_attempts: 2,
+        };
+
+        let candidates = config.candidates("https://example.com/lyrics.ttml");
+        assert_eq!(candidates.len(), 2);
+        assert_eq!(candidates.last().unwrap(), "https://example.com/lyrics.ttml");
+    }
+
+    #[test]
+    fn no_matching_rule_falls_back_to_original_url_only() {
+        let config = MirrorConfig::default();
+        let candidates = config.candidates("https://example.com/lyrics.ttml");
+        assert_eq!(candidates, vec!["https://example.com/lyrics.ttml".to_string()]);
+    }
+
+    #[test]
+    fn max_attempts_of_one_still_keeps_original_url() {
+        let config = MirrorConfig {
+            rules: vec![MirrorRule {
+                pattern: "example.com".to_string(),
+                rewrites: vec!["https://mirror-a/{url}".to_string()],
+            }],
+            timeout_secs: default_timeout_secs(),
+            max_attempts: 1,
+        };
+
+        let candidates = config.candidates("https://example.com/lyrics.ttml");
+        assert_eq!(candidates, vec!["https://example.com/lyrics.ttml".to_string()]);
+    }
+}
+
+/// 按配置依次尝试候选地址下载 TTML 内容，任一候选网络出错或返回非 2xx 时重试下一个。
+pub async fn fetch_with_fallback(
+    http_client: &Client,
+    original_url: &str,
+    config: &MirrorConfig,
+) -> Result<String> {
+    let candidates = config.candidates(original_url);
+    let timeout = Duration::from_secs(config.timeout_secs);
+
+    let mut last_error = None;
+    for candidate in &candidates {
+        log::info!("尝试从 {} 下载 TTML...", candidate);
+        match http_client.get(candidate).timeout(timeout).send().await {
+            Ok(response) if response.status().is_success() => match response.text().await {
+                Ok(text) => {
+                    log::info!("下载成功，最终使用的来源: {}", candidate);
+                    return Ok(text);
+                }
+                Err(e) => {
+                    log::warn!("从 {} 读取响应体失败: {:?}", candidate, e);
+                    last_error = Some(e.into());
+                }
+            },
+            Ok(response) => {
+                log::warn!("从 {} 下载返回非成功状态: {}", candidate, response.status());
+                last_error = Some(anyhow::anyhow!(
+                    "{} 返回状态 {}",
+                    candidate,
+                    response.status()
+                ));
+            }
+            Err(e) => {
+                log::warn!("从 {} 下载失败: {:?}", candidate, e);
+                last_error = Some(e.into());
+            }
+        }
+    }
+
+    match last_error {
+        Some(e) => Err(e).context("所有候选下载源均失败"),
+        None => bail!("没有可尝试的下载候选地址"),
+    }
+}
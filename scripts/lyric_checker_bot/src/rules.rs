@@ -0,0 +1,530 @@
+//! 基于元数据与歌词行的声明式路由规则语言。
+//!
+//! 维护者可以在仓库内配置一组 `rule -> action` 规则，例如“英语歌词且艺术家已知则打标签，
+//! 缺少翻译则直接拒绝”，而无需为每一种策略重新编译机器人。
+//!
+//! 语法（`S` 为规则起始符）：
+//! ```text
+//! S -> A or S | A
+//! A -> B and A | B
+//! B -> ( S ) | C
+//! C -> not C | P
+//! P -> field in [v1, v2, ...] | field contains "text" | field > N | bare_flag
+//! ```
+
+use std::fmt;
+
+use anyhow::{Context, Result, bail};
+use ttml_processor::MetadataStore;
+use ttml_processor::types::LyricLine;
+
+/// 规则解析或求值过程中产生的错误，附带足够定位问题的上下文。
+#[derive(Debug)]
+pub struct RuleError(pub String);
+
+impl fmt::Display for RuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for RuleError {}
+
+/// 规则语言的抽象语法树。
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuleExpr {
+    Or(Box<RuleExpr>, Box<RuleExpr>),
+    And(Box<RuleExpr>, Box<RuleExpr>),
+    Not(Box<RuleExpr>),
+    Predicate(Predicate),
+}
+
+/// 单个断言。
+#[derive(Debug, Clone, PartialEq)]
+pub enum Predicate {
+    /// `field in [v1, v2, ...]`
+    In { field: String, values: Vec<String> },
+    /// `field contains "text"`
+    Contains { field: String, text: String },
+    /// 裸标志，如 `has_translation`、`is_line_timing`。
+    Flag(String),
+    /// `field > N` 这类数值比较，目前仅支持 `syllable_count`。
+    GreaterThan { field: String, threshold: i64 },
+}
+
+/// 提交命中规则后应执行的动作。
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuleAction {
+    Label(String),
+    Decline { message: String },
+    RequireReview,
+}
+
+/// 一条完整的 `rule -> action` 配置项。
+#[derive(Debug, Clone)]
+pub struct RoutingRule {
+    pub expr: RuleExpr,
+    pub action: RuleAction,
+}
+
+/// 规则求值所需的上下文：元数据与已解析的歌词行。
+pub struct EvalContext<'a> {
+    pub metadata: &'a MetadataStore,
+    pub lines: &'a [LyricLine],
+}
+
+/// 解析一条规则表达式文本为 AST。
+pub fn parse_rule(input: &str) -> Result<RuleExpr, RuleError> {
+    let tokens = tokenize(input)?;
+    let mut parser = Parser { tokens, pos: 0 };
+    let expr = parser.parse_or()?;
+    if parser.pos != parser.tokens.len() {
+        return Err(RuleError(format!(
+            "规则末尾存在无法解析的多余内容: {:?}",
+            &parser.tokens[parser.pos..]
+        )));
+    }
+    Ok(expr)
+}
+
+/// 对解析后的规则表达式求值，返回是否命中。
+pub fn evaluate(expr: &RuleExpr, ctx: &EvalContext) -> Result<bool, RuleError> {
+    match expr {
+        RuleExpr::Or(lhs, rhs) => Ok(evaluate(lhs, ctx)? || evaluate(rhs, ctx)?),
+        RuleExpr::And(lhs, rhs) => Ok(evaluate(lhs, ctx)? && evaluate(rhs, ctx)?),
+        RuleExpr::Not(inner) => Ok(!evaluate(inner, ctx)?),
+        RuleExpr::Predicate(predicate) => evaluate_predicate(predicate, ctx),
+    }
+}
+
+fn evaluate_predicate(predicate: &Predicate, ctx: &EvalContext) -> Result<bool, RuleError> {
+    match predicate {
+        Predicate::In { field, values } => {
+            let actual = resolve_field(field, ctx)?;
+            Ok(actual
+                .map(|value| values.iter().any(|v| v.eq_ignore_ascii_case(&value)))
+                .unwrap_or(false))
+        }
+        Predicate::Contains { field, text } => {
+            let actual = resolve_field(field, ctx)?;
+            Ok(actual
+                .map(|value| value.to_lowercase().contains(&text.to_lowercase()))
+                .unwrap_or(false))
+        }
+        Predicate::Flag(flag) => evaluate_flag(flag, ctx),
+        Predicate::GreaterThan { field, threshold } => match field.as_str() {
+            "syllable_count" => {
+                let count: i64 = ctx
+                    .lines
+                    .iter()
+                    .flat_map(|line| &line.words)
+                    .count() as i64;
+                Ok(count > *threshold)
+            }
+            other => Err(RuleError(format!("未知的数值字段: {}", other))),
+        },
+    }
+}
+
+/// 将字段名解析为元数据中的取值。元数据字段（artist、language、album……）逐一查询。
+fn resolve_field(field: &str, ctx: &EvalContext) -> Result<Option<String>, RuleError> {
+    match ctx.metadata.get_single(field) {
+        Some(value) => Ok(Some(value)),
+        None => {
+            if KNOWN_METADATA_FIELDS.contains(&field) {
+                Ok(None)
+            } else {
+                Err(RuleError(format!("未知的元数据字段: {}", field)))
+            }
+        }
+    }
+}
+
+const KNOWN_METADATA_FIELDS: &[&str] = &["artist", "language", "album", "title"];
+
+fn evaluate_flag(flag: &str, ctx: &EvalContext) -> Result<bool, RuleError> {
+    match flag {
+        "has_translation" => Ok(ctx
+            .lines
+            .iter()
+            .any(|line| line.translations.as_ref().is_some_and(|t| !t.is_empty()))),
+        "is_line_timing" => Ok(ctx
+            .lines
+            .iter()
+            .all(|line| line.words.len() <= 1)),
+        other => Err(RuleError(format!("未知的标志: {}", other))),
+    }
+}
+
+// ---- 词法与语法分析 ----
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Number(i64),
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    Comma,
+    And,
+    Or,
+    Not,
+    In,
+    Contains,
+    Gt,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, RuleError> {
+    let mut tokens = Vec::new();
+    let chars: Vec<char> = input.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            ' ' | '\t' | '\n' | '\r' => i += 1,
+            '(' => {
+                tokens.push(Token::LParen);
+                i += 1;
+            }
+            ')' => {
+                tokens.push(Token::RParen);
+                i += 1;
+            }
+            '[' => {
+                tokens.push(Token::LBracket);
+                i += 1;
+            }
+            ']' => {
+                tokens.push(Token::RBracket);
+                i += 1;
+            }
+            ',' => {
+                tokens.push(Token::Comma);
+                i += 1;
+            }
+            '>' => {
+                tokens.push(Token::Gt);
+                i += 1;
+            }
+            '"' => {
+                let start = i + 1;
+                let mut j = start;
+                while j < chars.len() && chars[j] != '"' {
+                    j += 1;
+                }
+                if j >= chars.len() {
+                    return Err(RuleError("字符串字面量未闭合".to_string()));
+                }
+                tokens.push(Token::Str(chars[start..j].iter().collect()));
+                i = j + 1;
+            }
+            _ if c.is_ascii_digit() => {
+                let start = i;
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+                let number_text: String = chars[start..i].iter().collect();
+                let number = number_text
+                    .parse()
+                    .map_err(|_| RuleError(format!("无法解析的数字: {}", number_text)))?;
+                tokens.push(Token::Number(number));
+            }
+            _ if c.is_alphanumeric() || c == '_' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                tokens.push(match word.as_str() {
+                    "and" => Token::And,
+                    "or" => Token::Or,
+                    "not" => Token::Not,
+                    "in" => Token::In,
+                    "contains" => Token::Contains,
+                    _ => Token::Ident(word),
+                });
+            }
+            other => {
+                return Err(RuleError(format!("无法识别的字符: {:?}", other)));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn advance(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: &Token) -> Result<(), RuleError> {
+        match self.advance() {
+            Some(ref token) if token == expected => Ok(()),
+            other => Err(RuleError(format!(
+                "期望 {:?}，但得到 {:?}",
+                expected, other
+            ))),
+        }
+    }
+
+    // S -> A or S | A
+    fn parse_or(&mut self) -> Result<RuleExpr, RuleError> {
+        let lhs = self.parse_and()?;
+        if matches!(self.peek(), Some(Token::Or)) {
+            self.advance();
+            let rhs = self.parse_or()?;
+            Ok(RuleExpr::Or(Box::new(lhs), Box::new(rhs)))
+        } else {
+            Ok(lhs)
+        }
+    }
+
+    // A -> B and A | B
+    fn parse_and(&mut self) -> Result<RuleExpr, RuleError> {
+        let lhs = self.parse_not()?;
+        if matches!(self.peek(), Some(Token::And)) {
+            self.advance();
+            let rhs = self.parse_and()?;
+            Ok(RuleExpr::And(Box::new(lhs), Box::new(rhs)))
+        } else {
+            Ok(lhs)
+        }
+    }
+
+    // C -> not C | P, B -> ( S ) | C
+    fn parse_not(&mut self) -> Result<RuleExpr, RuleError> {
+        if matches!(self.peek(), Some(Token::Not)) {
+            self.advance();
+            let inner = self.parse_not()?;
+            return Ok(RuleExpr::Not(Box::new(inner)));
+        }
+
+        if matches!(self.peek(), Some(Token::LParen)) {
+            self.advance();
+            let inner = self.parse_or()?;
+            self.expect(&Token::RParen)?;
+            return Ok(inner);
+        }
+
+        self.parse_predicate()
+    }
+
+    fn parse_predicate(&mut self) -> Result<RuleExpr, RuleError> {
+        let field = match self.advance() {
+            Some(Token::Ident(name)) => name,
+            other => return Err(RuleError(format!("期望字段名或标志，得到 {:?}", other))),
+        };
+
+        match self.peek() {
+            Some(Token::In) => {
+                self.advance();
+                self.expect(&Token::LBracket)?;
+                let mut values = Vec::new();
+                loop {
+                    match self.advance() {
+                        Some(Token::Str(s)) => values.push(s),
+                        Some(Token::Ident(s)) => values.push(s),
+                        other => {
+                            return Err(RuleError(format!(
+                                "期望列表中的值，得到 {:?}",
+                                other
+                            )));
+                        }
+                    }
+                    match self.peek() {
+                        Some(Token::Comma) => {
+                            self.advance();
+                        }
+                        _ => break,
+                    }
+                }
+                self.expect(&Token::RBracket)?;
+                Ok(RuleExpr::Predicate(Predicate::In { field, values }))
+            }
+            Some(Token::Contains) => {
+                self.advance();
+                match self.advance() {
+                    Some(Token::Str(text)) => {
+                        Ok(RuleExpr::Predicate(Predicate::Contains { field, text }))
+                    }
+                    other => Err(RuleError(format!("期望字符串字面量，得到 {:?}", other))),
+                }
+            }
+            Some(Token::Gt) => {
+                self.advance();
+                match self.advance() {
+                    Some(Token::Number(n)) => {
+                        Ok(RuleExpr::Predicate(Predicate::GreaterThan { field, threshold: n }))
+                    }
+                    other => Err(RuleError(format!("期望数字，得到 {:?}", other))),
+                }
+            }
+            _ => Ok(RuleExpr::Predicate(Predicate::Flag(field))),
+        }
+    }
+}
+
+/// 从仓库内的配置文件加载 `rule -> action` 列表，格式为每行 `<规则文本> => <动作>`。
+///
+/// 动作语法：`label: <名称>` / `decline: <提示文本>` / `require_review`。
+pub fn load_rule_config(raw: &str) -> Result<Vec<RoutingRule>> {
+    let mut rules = Vec::new();
+    for (line_no, line) in raw.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (rule_text, action_text) = line
+            .split_once("=>")
+            .with_context(|| format!("第 {} 行缺少 '=>' 分隔符", line_no + 1))?;
+        let expr = parse_rule(rule_text.trim())
+            .with_context(|| format!("第 {} 行规则解析失败", line_no + 1))?;
+        let action = parse_action(action_text.trim())
+            .with_context(|| format!("第 {} 行动作解析失败", line_no + 1))?;
+        rules.push(RoutingRule { expr, action });
+    }
+    Ok(rules)
+}
+
+fn parse_action(text: &str) -> Result<RuleAction> {
+    if let Some(label) = text.strip_prefix("label:") {
+        return Ok(RuleAction::Label(label.trim().to_string()));
+    }
+    if let Some(message) = text.strip_prefix("decline:") {
+        return Ok(RuleAction::Decline {
+            message: message.trim().to_string(),
+        });
+    }
+    if text == "require_review" {
+        return Ok(RuleAction::RequireReview);
+    }
+    bail!("无法识别的动作: {}", text)
+}
+
+/// 按顺序求值规则列表，返回第一条命中的动作。
+///
+/// 单条规则引用了某份提交元数据中不存在的字段时，只跳过该规则并记录警告，
+/// 不能让它中断其余规则的求值——否则一条写得不够健壮的规则就能悄悄让
+/// 整个路由表失效。
+pub fn first_matching_action(
+    rules: &[RoutingRule],
+    ctx: &EvalContext,
+) -> Result<Option<RuleAction>, RuleError> {
+    for (index, rule) in rules.iter().enumerate() {
+        match evaluate(&rule.expr, ctx) {
+            Ok(true) => return Ok(Some(rule.action.clone())),
+            Ok(false) => {}
+            Err(e) => {
+                log::warn!("第 {} 条路由规则求值失败，跳过该规则: {}", index + 1, e);
+            }
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ttml_processor::MetadataStore;
+
+    fn empty_ctx(metadata: &MetadataStore) -> EvalContext<'_> {
+        EvalContext {
+            metadata,
+            lines: &[],
+        }
+    }
+
+    #[test]
+    fn parses_and_or_not_with_precedence() {
+        let expr = parse_rule("not has_translation and language in [\"en\"] or is_line_timing")
+            .expect("规则应当能解析");
+        assert_eq!(
+            expr,
+            RuleExpr::Or(
+                Box::new(RuleExpr::And(
+                    Box::new(RuleExpr::Not(Box::new(RuleExpr::Predicate(
+                        Predicate::Flag("has_translation".to_string())
+                    )))),
+                    Box::new(RuleExpr::Predicate(Predicate::In {
+                        field: "language".to_string(),
+                        values: vec!["en".to_string()],
+                    })),
+                )),
+                Box::new(RuleExpr::Predicate(Predicate::Flag(
+                    "is_line_timing".to_string()
+                ))),
+            )
+        );
+    }
+
+    #[test]
+    fn rejects_trailing_garbage() {
+        let err = parse_rule("is_line_timing extra_token").unwrap_err();
+        assert!(err.0.contains("多余内容"));
+    }
+
+    #[test]
+    fn evaluates_flag_on_empty_lines() {
+        let metadata = MetadataStore::new();
+        let ctx = empty_ctx(&metadata);
+        // 没有歌词行时，“所有行都是逐行计时”在空集合上平凡成立。
+        assert!(evaluate_flag("is_line_timing", &ctx).unwrap());
+        assert!(!evaluate_flag("has_translation", &ctx).unwrap());
+    }
+
+    #[test]
+    fn load_rule_config_parses_each_action_kind() {
+        let raw = "\
+            # 注释行应当被跳过\n\
+            language in [\"en\"] => label: needs-translation\n\
+            not has_translation => decline: 缺少翻译\n\
+            is_line_timing => require_review\n\
+        ";
+        let rules = load_rule_config(raw).expect("配置应当能解析");
+        assert_eq!(rules.len(), 3);
+        assert_eq!(rules[0].action, RuleAction::Label("needs-translation".to_string()));
+        assert_eq!(
+            rules[1].action,
+            RuleAction::Decline {
+                message: "缺少翻译".to_string()
+            }
+        );
+        assert_eq!(rules[2].action, RuleAction::RequireReview);
+    }
+
+    #[test]
+    fn a_failing_rule_does_not_block_later_rules() {
+        let metadata = MetadataStore::new();
+        let ctx = empty_ctx(&metadata);
+        let rules = vec![
+            RoutingRule {
+                // 引用一个未知字段，求值时应返回错误。
+                expr: RuleExpr::Predicate(Predicate::In {
+                    field: "not_a_real_field".to_string(),
+                    values: vec!["x".to_string()],
+                }),
+                action: RuleAction::Label("unreachable".to_string()),
+            },
+            RoutingRule {
+                expr: RuleExpr::Predicate(Predicate::Flag("is_line_timing".to_string())),
+                action: RuleAction::RequireReview,
+            },
+        ];
+
+        let action = first_matching_action(&rules, &ctx).expect("不应向上传播单条规则的错误");
+        assert_eq!(action, Some(RuleAction::RequireReview));
+    }
+}
@@ -0,0 +1,417 @@
+//! GitHub Webhook 长驻服务模式。
+//!
+//! 与 `main()` 中的一次性批处理模式不同，本模块启动一个常驻的 TCP 监听器，
+//! 直接接收 GitHub 推送的 `issues` / `issue_comment` Webhook 事件并立即处理，
+//! 从而避免等待下一次定时轮询。
+
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use hmac::{Hmac, Mac};
+use reqwest::Client;
+use sha2::Sha256;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{Mutex, Semaphore, mpsc};
+
+use crate::github_api::GitHubClient;
+use crate::rules::RoutingRule;
+use crate::{DedupContext, process_issue};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// 请求体大小上限。监听地址是 `0.0.0.0`，任何能连上的调用方都可以在签名校验之前
+/// 先声称一个巨大的 `Content-Length`，几百 KB 足够容纳 GitHub 实际会发来的负载，
+/// 避免未经认证的连接迫使服务端分配无上限的内存。
+const MAX_BODY_BYTES: usize = 512 * 1024;
+
+/// 单个连接从建立到处理完毕的总时限。listener 绑定 `0.0.0.0`，一个每次只挤牙膏式
+/// 发送几个字节的连接如果不设超时会一直占着连接与其对应的异步任务，属于
+/// slow-loris 式的资源耗尽。
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 允许同时在途处理的连接数上限，配合上面的超时共同限制单机能被占用的连接/任务数。
+const MAX_CONCURRENT_CONNECTIONS: usize = 64;
+
+/// 启动 Webhook 服务所需的运行时配置。
+pub struct ServeConfig {
+    pub addr: SocketAddr,
+    pub webhook_secret: String,
+    pub worker_count: usize,
+}
+
+impl ServeConfig {
+    /// 从环境变量读取服务配置：
+    /// - `WEBHOOK_LISTEN_ADDR`（默认 `0.0.0.0:8080`）
+    /// - `WEBHOOK_SECRET`（必须设置，用于校验 `X-Hub-Signature-256`）
+    /// - `WEBHOOK_WORKERS`（默认 4）
+    pub fn from_env() -> Result<Self> {
+        let addr = std::env::var("WEBHOOK_LISTEN_ADDR")
+            .unwrap_or_else(|_| "0.0.0.0:8080".to_string())
+            .parse()
+            .context("WEBHOOK_LISTEN_ADDR 格式无效")?;
+        let webhook_secret =
+            std::env::var("WEBHOOK_SECRET").context("未设置 WEBHOOK_SECRET 环境变量")?;
+        let worker_count = std::env::var("WEBHOOK_WORKERS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(4);
+
+        Ok(Self {
+            addr,
+            webhook_secret,
+            worker_count,
+        })
+    }
+}
+
+/// 一个待处理的 Webhook 任务：解析出的 Issue 编号用于避免同一 Issue 被并发处理。
+struct WebhookJob {
+    issue: octocrab::models::issues::Issue,
+}
+
+/// 启动长驻 Webhook 服务，阻塞直至进程被终止。
+pub async fn run_server(
+    config: ServeConfig,
+    http_client: Client,
+    github: GitHubClient,
+    root_path: std::path::PathBuf,
+    dedup_ctx: DedupContext,
+    routing_rules: Arc<Vec<RoutingRule>>,
+) -> Result<()> {
+    let listener = TcpListener::bind(config.addr)
+        .await
+        .with_context(|| format!("无法监听 {}", config.addr))?;
+    log::info!("Webhook 服务已启动，监听 {}", config.addr);
+
+    let (tx, rx) = mpsc::channel::<WebhookJob>(256);
+    let rx = Arc::new(Mutex::new(rx));
+    let in_flight: Arc<Mutex<HashSet<u64>>> = Arc::new(Mutex::new(HashSet::new()));
+
+    // 多个 worker 共享同一个队列，按 Issue 编号去重，避免同一 Issue 的并发请求互相竞争。
+    for _ in 0..config.worker_count {
+        let rx = Arc::clone(&rx);
+        let http_client = http_client.clone();
+        let github = github.clone();
+        let root_path = root_path.clone();
+        let in_flight = Arc::clone(&in_flight);
+        let dedup_ctx = dedup_ctx.clone();
+        let routing_rules = Arc::clone(&routing_rules);
+        tokio::spawn(async move {
+            worker_loop(rx, http_client, github, root_path, dedup_ctx, routing_rules, in_flight).await
+        });
+    }
+
+    let secret = Arc::new(config.webhook_secret);
+    let connection_limiter = Arc::new(Semaphore::new(MAX_CONCURRENT_CONNECTIONS));
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let tx = tx.clone();
+        let secret = Arc::clone(&secret);
+        let connection_limiter = Arc::clone(&connection_limiter);
+        tokio::spawn(async move {
+            let Ok(_permit) = connection_limiter.try_acquire_owned() else {
+                log::warn!("并发连接数已达上限，拒绝来自 {} 的连接。", peer);
+                return;
+            };
+
+            match tokio::time::timeout(CONNECTION_TIMEOUT, handle_connection(stream, &secret, tx))
+                .await
+            {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => {
+                    log::warn!("处理来自 {} 的 Webhook 请求失败: {:?}", peer, e);
+                }
+                Err(_) => {
+                    log::warn!("处理来自 {} 的 Webhook 请求超时，已中止。", peer);
+                }
+            }
+        });
+    }
+}
+
+/// 串行处理队列中的任务；同一 Issue 编号已有任务在处理时，新到的任务直接丢弃，避免竞态。
+async fn worker_loop(
+    rx: Arc<Mutex<mpsc::Receiver<WebhookJob>>>,
+    http_client: Client,
+    github: GitHubClient,
+    root_path: std::path::PathBuf,
+    dedup_ctx: DedupContext,
+    routing_rules: Arc<Vec<RoutingRule>>,
+    in_flight: Arc<Mutex<HashSet<u64>>>,
+) {
+    loop {
+        let job = {
+            let mut rx = rx.lock().await;
+            match rx.recv().await {
+                Some(job) => job,
+                None => break,
+            }
+        };
+        let issue_number = job.issue.number;
+        {
+            let mut guard = in_flight.lock().await;
+            if !guard.insert(issue_number) {
+                log::info!("Issue #{} 已在处理中，跳过本次 Webhook 事件。", issue_number);
+                continue;
+            }
+        }
+
+        log::info!("通过 Webhook 开始处理 Issue #{}", issue_number);
+        if let Err(e) = process_issue(
+            &job.issue,
+            http_client.clone(),
+            github.clone(),
+            &root_path,
+            dedup_ctx.clone(),
+            Arc::clone(&routing_rules),
+        )
+        .await
+        {
+            log::error!("处理 Issue #{} 失败: {:?}", issue_number, e);
+        }
+
+        in_flight.lock().await.remove(&issue_number);
+    }
+}
+
+/// 处理单个 TCP 连接：读取一个 HTTP 请求，校验签名后将解析出的事件投递到队列。
+async fn handle_connection(
+    mut stream: TcpStream,
+    secret: &str,
+    tx: mpsc::Sender<WebhookJob>,
+) -> Result<()> {
+    let request = read_http_request(&mut stream).await?;
+
+    let signature = request
+        .headers
+        .get("x-hub-signature-256")
+        .cloned()
+        .unwrap_or_default();
+    if !verify_signature(secret, &request.body, &signature) {
+        log::warn!("Webhook 签名校验失败，已拒绝请求。");
+        write_response(&mut stream, 401, "signature mismatch").await?;
+        return Ok(());
+    }
+
+    let event = request
+        .headers
+        .get("x-github-event")
+        .cloned()
+        .unwrap_or_default();
+
+    match event.as_str() {
+        "issues" => {
+            let payload: IssuesEventPayload = serde_json::from_slice(&request.body)
+                .context("解析 issues Webhook 负载失败")?;
+            if matches!(payload.action.as_str(), "opened" | "edited") {
+                tx.send(WebhookJob {
+                    issue: payload.issue,
+                })
+                .await
+                .ok();
+            }
+        }
+        "issue_comment" => {
+            let payload: IssueCommentEventPayload = serde_json::from_slice(&request.body)
+                .context("解析 issue_comment Webhook 负载失败")?;
+            tx.send(WebhookJob {
+                issue: payload.issue,
+            })
+            .await
+            .ok();
+        }
+        other => {
+            log::debug!("忽略不关心的 Webhook 事件类型: {}", other);
+        }
+    }
+
+    write_response(&mut stream, 200, "ok").await
+}
+
+#[derive(serde::Deserialize)]
+struct IssuesEventPayload {
+    action: String,
+    issue: octocrab::models::issues::Issue,
+}
+
+#[derive(serde::Deserialize)]
+struct IssueCommentEventPayload {
+    issue: octocrab::models::issues::Issue,
+}
+
+struct ParsedRequest {
+    headers: std::collections::HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+/// 解析最小化的 HTTP/1.1 请求：请求行 + 头部 + 按 `Content-Length` 读取的请求体。
+async fn read_http_request(stream: &mut TcpStream) -> Result<ParsedRequest> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let header_end = loop {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            bail!("连接在请求头读取完毕前关闭");
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        if let Some(pos) = find_subslice(&buf, b"\r\n\r\n") {
+            break pos + 4;
+        }
+        if buf.len() > 64 * 1024 {
+            bail!("请求头过大");
+        }
+    };
+
+    let header_text = String::from_utf8_lossy(&buf[..header_end]);
+    let mut headers = std::collections::HashMap::new();
+    for line in header_text.split("\r\n").skip(1) {
+        if let Some((key, value)) = line.split_once(':') {
+            headers.insert(key.trim().to_ascii_lowercase(), value.trim().to_string());
+        }
+    }
+
+    let content_length: usize = headers
+        .get("content-length")
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(0);
+    if content_length > MAX_BODY_BYTES {
+        bail!(
+            "请求体过大: Content-Length={} 超过上限 {} 字节",
+            content_length,
+            MAX_BODY_BYTES
+        );
+    }
+
+    let mut body = buf[header_end..].to_vec();
+    while body.len() < content_length {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            bail!("连接在请求体读取完毕前关闭");
+        }
+        body.extend_from_slice(&chunk[..n]);
+        if body.len() > MAX_BODY_BYTES {
+            bail!("请求体超过上限 {} 字节", MAX_BODY_BYTES);
+        }
+    }
+    body.truncate(content_length);
+
+    Ok(ParsedRequest { headers, body })
+}
+
+fn find_subslice(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// 校验 `X-Hub-Signature-256: sha256=<hex>` 头部与共享密钥对请求体计算出的 HMAC 是否一致。
+fn verify_signature(secret: &str, body: &[u8], header_value: &str) -> bool {
+    let Some(expected_hex) = header_value.strip_prefix("sha256=") else {
+        return false;
+    };
+
+    let mut mac = match HmacSha256::new_from_slice(secret.as_bytes()) {
+        Ok(mac) => mac,
+        Err(_) => return false,
+    };
+    mac.update(body);
+    let computed = mac.finalize().into_bytes();
+    let computed_hex = hex_encode(&computed);
+
+    // 固定时间比较，避免通过响应时间差异泄露签名信息。
+    constant_time_eq(computed_hex.as_bytes(), expected_hex.as_bytes())
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+async fn write_response(stream: &mut TcpStream, status: u16, body: &str) -> Result<()> {
+    let reason = if status == 200 { "OK" } else { "Unauthorized" };
+    let response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 通过一对回环 TCP 连接把 `raw` 喂给 `read_http_request`，模拟真实客户端发来的字节流。
+    async fn read_request_from_bytes(raw: &[u8]) -> Result<ParsedRequest> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let raw = raw.to_vec();
+        let client = tokio::spawn(async move {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream.write_all(&raw).await.unwrap();
+            stream.shutdown().await.unwrap();
+        });
+        let (mut server_stream, _) = listener.accept().await.unwrap();
+        let result = read_http_request(&mut server_stream).await;
+        client.await.unwrap();
+        result
+    }
+
+    #[tokio::test]
+    async fn parses_headers_and_body_by_content_length() {
+        let raw = b"POST / HTTP/1.1\r\nContent-Length: 5\r\nX-Github-Event: issues\r\n\r\nhello";
+        let parsed = read_request_from_bytes(raw).await.unwrap();
+        assert_eq!(parsed.body, b"hello");
+        assert_eq!(parsed.headers.get("x-github-event").unwrap(), "issues");
+    }
+
+    #[tokio::test]
+    async fn rejects_content_length_over_cap() {
+        let raw = format!(
+            "POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            MAX_BODY_BYTES + 1
+        );
+        let result = read_request_from_bytes(raw.as_bytes()).await;
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn verify_signature_accepts_matching_hmac_and_rejects_tampering() {
+        let secret = "shared-secret";
+        let body = b"{\"hello\":\"world\"}";
+        let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(body);
+        let header = format!("sha256={}", hex_encode(&mac.finalize().into_bytes()));
+
+        assert!(verify_signature(secret, body, &header));
+        assert!(!verify_signature(secret, body, "sha256=deadbeef"));
+        assert!(!verify_signature(secret, b"tampered body", &header));
+        assert!(!verify_signature("wrong-secret", body, &header));
+    }
+
+    #[test]
+    fn verify_signature_rejects_header_without_sha256_prefix() {
+        assert!(!verify_signature("secret", b"body", "not-a-valid-header"));
+    }
+
+    #[test]
+    fn constant_time_eq_matches_only_identical_byte_sequences() {
+        assert!(constant_time_eq(b"abc", b"abc"));
+        assert!(!constant_time_eq(b"abc", b"abd"));
+        assert!(!constant_time_eq(b"abc", b"ab"));
+    }
+}